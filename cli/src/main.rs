@@ -5,7 +5,7 @@ use std::{
 
 use clap::Parser;
 use color_eyre::eyre::{bail, Result, WrapErr};
-use tabled::{Table, Tabled};
+use tabled::Tabled;
 
 #[derive(Parser, Debug)]
 #[command(version, about, name = "KCL Compiler", long_about = None)]
@@ -36,21 +36,35 @@ fn main() -> Result<()> {
     let args = Args::parse();
     let source_code = args.read_input_source_code()?;
 
-    // Parse the KCL file and print results.
-    match compiler::parse(&source_code) {
-        Ok((input, ast)) if input.fragment().is_empty() => {
-            print_program_analysis(ast);
-        }
-        Ok((remaining_input, _ast)) => {
-            bail!("Part of your source code was not parsed: {remaining_input}")
-        }
-        Err(errors) => {
-            eprintln!("Your program did not parse. Here is the chain of parser errors. This is similar to a stack trace: the top row is the deepest parser in the parse tree. The bottom row is the parse tree root.");
-            let table = Table::new(errors);
-            eprintln!("{table}");
-            std::process::exit(1)
+    // Parse the KCL file and print results. Parsing is error-resilient, so we
+    // get back whatever functions did parse alongside a diagnostic for every
+    // independent error.
+    let (ast, diagnostics) = compiler::parse(&source_code);
+    if !diagnostics.is_empty() {
+        eprintln!("Your program did not parse.\n");
+        eprint!(
+            "{}",
+            compiler::displayable_error::render(&source_code, diagnostics.as_slice())
+        );
+        std::process::exit(1);
+    }
+
+    // The program parsed, so run the semantic pass before reporting on it. A
+    // well-typed program carries on to the analysis below; any type errors are
+    // reported against their source range and stop the run.
+    let type_errors = ast.typecheck();
+    if !type_errors.is_empty() {
+        eprintln!("Your program has type errors.\n");
+        for err in &type_errors {
+            eprintln!(
+                "{}:{}: {}",
+                err.range.start_line, err.range.start_column, err.kind
+            );
         }
+        std::process::exit(1);
     }
+
+    print_program_analysis(ast);
     Ok(())
 }
 
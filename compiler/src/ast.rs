@@ -1,13 +1,15 @@
 //! Abstract syntax tree that KCL files get parsed into.
 use std::fmt;
 
-use crate::parser::Input;
+use crate::intern::{Interner, Symbol};
+use crate::parser::{Input, NodeId};
 
 /// For now, a KCL program is just a series of function definitions.
-/// TODO: It should support also:
-///  - Comments
-///  - Import statements
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// Comments are not semantically meaningful, so they are dropped here, and
+/// `import` statements are not part of this grammar: the parser reports them as
+/// errors. The lossless [`crate::lossless`] tree preserves both for
+/// round-tripping.
+#[derive(Debug, Clone, PartialEq)]
 pub struct AbstractSyntaxTree<'i> {
     pub functions: Vec<FnDef<'i>>,
 }
@@ -24,6 +26,15 @@ impl<'i> fmt::Display for Identifier<'i> {
     }
 }
 
+impl<'i> Identifier<'i> {
+    /// Intern this identifier's text into `interner`, returning a copyable
+    /// [`Symbol`] for O(1) comparison in later passes. The identifier keeps its
+    /// source span for diagnostics.
+    pub fn intern(&self, interner: &mut Interner) -> Symbol {
+        interner.intern(self.0.fragment())
+    }
+}
+
 // In tests, you can turn a Rust string into an identifier.
 // In prod, use the parser, because this does not guarantee that the string is a valid identifier.
 #[cfg(test)]
@@ -37,17 +48,39 @@ impl<'i> From<Input<'i>> for Identifier<'i> {
 impl<'i> Identifier<'i> {
     pub(crate) fn from_span(fragment: &'i str, offset: usize, line: u32) -> Self {
         // Safe, because we're only doing this in unit tests.
-        unsafe { Self(Input::new_from_raw_offset(offset, line, fragment, ())) }
+        unsafe {
+            Self(Input::new_from_raw_offset(
+                offset,
+                line,
+                fragment,
+                Default::default(),
+            ))
+        }
     }
 }
 
 /// Function definition
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct FnDef<'i> {
     pub fn_name: Identifier<'i>,
     pub params: Vec<Parameter<'i>>,
     pub return_type: Identifier<'i>,
     pub body: Expression<'i>,
+    /// Unique id minted while parsing, so later passes can hang diagnostics off
+    /// this specific definition. `None` for hand-built trees (e.g. in tests).
+    pub node_id: Option<NodeId>,
+}
+
+// The node id is parse-time identity, not part of the definition's meaning, so
+// two definitions with the same name, parameters, body and return type are
+// equal regardless of which ids they were assigned.
+impl<'i> PartialEq for FnDef<'i> {
+    fn eq(&self, other: &Self) -> bool {
+        self.fn_name == other.fn_name
+            && self.params == other.params
+            && self.return_type == other.return_type
+            && self.body == other.body
+    }
 }
 
 /// Parameters for declared functions
@@ -58,7 +91,7 @@ pub struct Parameter<'i> {
 }
 
 /// Function invocation
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FnInvocation<'i> {
     pub fn_name: Identifier<'i>,
     pub args: Vec<Expression<'i>>,
@@ -66,10 +99,24 @@ pub struct FnInvocation<'i> {
 
 /// Expressions can be evaluated (producing a value)
 /// or bound to identifiers by assignments.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression<'i> {
-    /// Numbers are expressions
+    /// Integer literals, e.g. a count or index.
     Number(u64),
+    /// Floating-point literals for fractional CAD dimensions and angles, e.g.
+    /// `3.5`, `-1.5` or `2.5e-2`.
+    Float(f64),
+    /// Double-quoted string literals, e.g. a label or file name.
+    Str(String),
+    /// Boolean literals `true` and `false`.
+    Bool(bool),
+    /// A conditional `if <cond> then <then_branch> else <else_branch>`.
+    /// Evaluates to whichever branch the condition selects.
+    If {
+        cond: Box<Expression<'i>>,
+        then_branch: Box<Expression<'i>>,
+        else_branch: Box<Expression<'i>>,
+    },
     /// Function invocations evaluate to their return value.
     FnInvocation(FnInvocation<'i>),
     /// A value bound to a name is an expression.
@@ -95,10 +142,20 @@ pub enum Operator {
     Sub,
     Mul,
     Div,
+    /// `==`
+    Eq,
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `<=`
+    Le,
+    /// `>=`
+    Ge,
 }
 
 /// Assigning a value to a binding, e.g. `n = 100`.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Assignment<'i> {
     pub identifier: Identifier<'i>,
     pub value: Expression<'i>,
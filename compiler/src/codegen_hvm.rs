@@ -0,0 +1,309 @@
+//! Lower the AST to HVM (Higher-order Virtual Machine) source text.
+//!
+//! HVM evaluates lambda-calculus terms with a parallel interaction-net
+//! reducer. By translating each KCL `FnDef` into a named HVM rule we get an
+//! execution backend "for free": the generated program can be handed to HVM
+//! and reduced. The lowering mirrors the AST closely — numbers become 60-bit
+//! unsigned literals, arithmetic becomes HVM's binary numeric operator node,
+//! and calls become a constructor/application spine over the callee.
+use std::fmt::Write;
+
+use crate::ast::{AbstractSyntaxTree, Expression, FnInvocation, Operator};
+
+/// An HVM term. This is a deliberately small subset of `hvm::syntax::Term`,
+/// covering only what the KCL AST can produce.
+#[derive(Debug, Clone)]
+enum Term {
+    /// An unsigned 60-bit numeric literal.
+    U6O { numb: u64 },
+    /// A floating-point numeric literal.
+    F6O { numb: f64 },
+    /// A string literal. HVM renders these as a built-in cons-list of chars.
+    Str { val: String },
+    /// A variable reference (already mangled to be HVM-safe).
+    Var { name: String },
+    /// A binary numeric operation, e.g. `(+ a b)`.
+    Op2 {
+        oper: Oper,
+        val0: Box<Term>,
+        val1: Box<Term>,
+    },
+    /// A `let <name> = <expr>; <body>` binding.
+    Let {
+        name: String,
+        expr: Box<Term>,
+        body: Box<Term>,
+    },
+    /// An application spine: the callee applied to its arguments.
+    App { func: String, args: Vec<Term> },
+}
+
+/// HVM's numeric operators: the arithmetic and comparison operators KCL
+/// supports, mapped one-to-one onto HVM's binary operator nodes.
+#[derive(Debug, Clone, Copy)]
+enum Oper {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl From<Operator> for Oper {
+    fn from(op: Operator) -> Self {
+        match op {
+            Operator::Add => Oper::Add,
+            Operator::Sub => Oper::Sub,
+            Operator::Mul => Oper::Mul,
+            Operator::Div => Oper::Div,
+            Operator::Eq => Oper::Eq,
+            Operator::Lt => Oper::Lt,
+            Operator::Gt => Oper::Gt,
+            Operator::Le => Oper::Le,
+            Operator::Ge => Oper::Ge,
+        }
+    }
+}
+
+impl Oper {
+    fn symbol(self) -> &'static str {
+        match self {
+            Oper::Add => "+",
+            Oper::Sub => "-",
+            Oper::Mul => "*",
+            Oper::Div => "/",
+            Oper::Eq => "==",
+            Oper::Lt => "<",
+            Oper::Gt => ">",
+            Oper::Le => "<=",
+            Oper::Ge => ">=",
+        }
+    }
+}
+
+impl Term {
+    /// Render this term as HVM source text.
+    fn render(&self, out: &mut String) {
+        match self {
+            Term::U6O { numb } => {
+                let _ = write!(out, "{numb}");
+            }
+            Term::F6O { numb } => {
+                // Use the debug formatting so the literal always keeps a decimal
+                // point (e.g. `3.0`) or an exponent (e.g. `1e16`), marking it as
+                // a float to HVM.
+                let _ = write!(out, "{numb:?}");
+            }
+            Term::Str { val } => {
+                // Escape the two characters that are significant inside an HVM
+                // string literal.
+                out.push('"');
+                for c in val.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        _ => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            Term::Var { name } => out.push_str(name),
+            Term::Op2 { oper, val0, val1 } => {
+                let _ = write!(out, "({} ", oper.symbol());
+                val0.render(out);
+                out.push(' ');
+                val1.render(out);
+                out.push(')');
+            }
+            Term::Let { name, expr, body } => {
+                let _ = write!(out, "let {name} = ");
+                expr.render(out);
+                out.push_str("; ");
+                body.render(out);
+            }
+            Term::App { func, args } => {
+                if args.is_empty() {
+                    out.push_str(func);
+                } else {
+                    out.push('(');
+                    out.push_str(func);
+                    for arg in args {
+                        out.push(' ');
+                        arg.render(out);
+                    }
+                    out.push(')');
+                }
+            }
+        }
+    }
+}
+
+/// Make an identifier safe to use in HVM source. HVM names allow letters,
+/// digits, `_` and `.`; anything else is escaped so that distinct KCL names
+/// can never collide.
+fn mangle(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '_' {
+            // Double a literal underscore so it can't be mistaken for an escape.
+            out.push_str("__");
+        } else if c.is_ascii_alphanumeric() || c == '.' {
+            out.push(c);
+        } else {
+            // Escape to the codepoint, terminated by `_` so a following hex
+            // digit can't run into it; the result stays unique.
+            let _ = write!(out, "_u{:x}_", c as u32);
+        }
+    }
+    out
+}
+
+/// Top-level rule names are capitalised and namespaced under `Kcl.` so they
+/// can never collide with bound variables or with the generated `Main` entry.
+fn rule_name(name: &str) -> String {
+    let mangled = mangle(name);
+    let mut chars = mangled.chars();
+    let capitalised: String = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => mangled,
+    };
+    format!("Kcl.{capitalised}")
+}
+
+/// A bound variable (parameter or `let` binding). Prefixed so it can never
+/// shadow a top-level rule name.
+fn var_name(name: &str) -> String {
+    format!("v_{}", mangle(name))
+}
+
+fn lower_expr(expr: &Expression) -> Term {
+    match expr {
+        Expression::Number(n) => Term::U6O { numb: *n },
+        Expression::Float(x) => Term::F6O { numb: *x },
+        Expression::Str(s) => Term::Str { val: s.clone() },
+        // HVM has no dedicated boolean type; represent `true`/`false` as 1/0.
+        Expression::Bool(b) => Term::U6O { numb: u64::from(*b) },
+        Expression::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => Term::App {
+            func: "If".to_owned(),
+            args: vec![
+                lower_expr(cond),
+                lower_expr(then_branch),
+                lower_expr(else_branch),
+            ],
+        },
+        Expression::Name(ident) => Term::Var {
+            name: var_name(&ident.to_string()),
+        },
+        Expression::Arithmetic { lhs, op, rhs } => Term::Op2 {
+            oper: Oper::from(*op),
+            val0: Box::new(lower_expr(lhs)),
+            val1: Box::new(lower_expr(rhs)),
+        },
+        Expression::LetIn { r#let, r#in } => {
+            // Introduce the bindings in order, innermost body last.
+            let mut body = lower_expr(r#in);
+            for assignment in r#let.iter().rev() {
+                body = Term::Let {
+                    name: var_name(&assignment.identifier.to_string()),
+                    expr: Box::new(lower_expr(&assignment.value)),
+                    body: Box::new(body),
+                };
+            }
+            body
+        }
+        Expression::FnInvocation(FnInvocation { fn_name, args }) => Term::App {
+            func: rule_name(&fn_name.to_string()),
+            args: args.iter().map(lower_expr).collect(),
+        },
+    }
+}
+
+impl AbstractSyntaxTree<'_> {
+    /// Lower the whole program to HVM source text, emitting one rule per
+    /// function definition plus a `Main` entry that runs the `main` function.
+    pub fn to_hvm(&self) -> String {
+        let mut out = String::new();
+        for func in &self.functions {
+            let name = rule_name(&func.fn_name.to_string());
+            out.push('(');
+            out.push_str(&name);
+            for param in &func.params {
+                out.push(' ');
+                out.push_str(&var_name(&param.name.to_string()));
+            }
+            out.push_str(") = ");
+            lower_expr(&func.body).render(&mut out);
+            out.push('\n');
+        }
+        // `if` lowers to an `If` application (see `lower_expr`), so emit the
+        // two rules that give it meaning when any function uses one. Booleans
+        // lower to 1/0, and the unused branch is erased with `*`.
+        if self.functions.iter().any(|func| uses_if(&func.body)) {
+            out.push_str("(If 1 t *) = t\n");
+            out.push_str("(If 0 * e) = e\n");
+        }
+        // Generated entrypoint. HVM reduces `Main`, which calls the KCL `main`.
+        out.push_str(&format!("Main = {}\n", rule_name("main")));
+        out
+    }
+}
+
+/// Whether an expression contains an `if`, so codegen knows to emit the `If`
+/// rules it lowers to.
+fn uses_if(expr: &Expression) -> bool {
+    match expr {
+        Expression::If { .. } => true,
+        Expression::Arithmetic { lhs, rhs, .. } => uses_if(lhs) || uses_if(rhs),
+        Expression::LetIn { r#let, r#in } => {
+            r#let.iter().any(|assignment| uses_if(&assignment.value)) || uses_if(r#in)
+        }
+        Expression::FnInvocation(FnInvocation { args, .. }) => args.iter().any(uses_if),
+        Expression::Number(_)
+        | Expression::Float(_)
+        | Expression::Str(_)
+        | Expression::Bool(_)
+        | Expression::Name(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Parse `src`, asserting it is free of parse errors, and lower it to HVM.
+    fn hvm(src: &str) -> String {
+        let (ast, diagnostics) = crate::parse(src);
+        assert!(diagnostics.is_empty(), "test program did not parse: {src}");
+        ast.to_hvm()
+    }
+
+    #[test]
+    fn lowers_arithmetic_function() {
+        assert_eq!(
+            hvm("double = (x: Int -> Int) => (x + x)"),
+            "(Kcl.Double v_x) = (+ v_x v_x)\nMain = Kcl.Main\n"
+        );
+    }
+
+    #[test]
+    fn lowers_let_binding_and_call() {
+        assert_eq!(
+            hvm("f = (x: Int -> Int) => let y = (x * 2) in g(y)"),
+            "(Kcl.F v_x) = let v_y = (* v_x 2); (Kcl.G v_y)\nMain = Kcl.Main\n"
+        );
+    }
+
+    #[test]
+    fn conditional_emits_if_rules() {
+        assert_eq!(
+            hvm("f = (x: Int -> Int) => if x > 0 then 1 else 2"),
+            "(Kcl.F v_x) = (If (> v_x 0) 1 2)\n(If 1 t *) = t\n(If 0 * e) = e\nMain = Kcl.Main\n"
+        );
+    }
+}
@@ -4,7 +4,6 @@ use nom::error::VerboseErrorKind;
 
 use crate::parser::Input;
 
-#[derive(tabled::Tabled)]
 pub struct DisplayableError<'i> {
     pub input: Input<'i>,
     pub error: DisplayErr,
@@ -21,25 +20,131 @@ impl<'i> DisplayableError<'i> {
             column: input.get_utf8_column(),
         }
     }
+
+    /// How many columns to underline. We point at the token that failed to
+    /// parse, i.e. up to the next whitespace, but always at least one column.
+    fn underline_len(&self) -> usize {
+        self.input
+            .fragment()
+            .split_whitespace()
+            .next()
+            .map(|tok| tok.chars().count().max(1))
+            .unwrap_or(1)
+    }
+}
+
+/// A collection of every diagnostic produced by one parse. Parsing is
+/// error-resilient, so a single pass can surface several independent problems;
+/// they are gathered here so the whole set can be reported at once rather than
+/// one-at-a-time.
+#[derive(Default)]
+pub struct Diagnostics<'i> {
+    errors: Vec<DisplayableError<'i>>,
+}
+
+impl<'i> Diagnostics<'i> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one more diagnostic.
+    pub fn push(&mut self, error: DisplayableError<'i>) {
+        self.errors.push(error);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// The diagnostics as a slice, for rendering with [`render`].
+    pub fn as_slice(&self) -> &[DisplayableError<'i>] {
+        &self.errors
+    }
 }
 
-pub struct DisplayErr(pub VerboseErrorKind);
+/// Render `errors` against the original `source` in the style of
+/// `codespan-reporting`: each error quotes its offending source line with a
+/// caret underline pointing at the exact span, a severity label, and the
+/// error message as a help note. This is far easier to read than the raw
+/// parser-frame table.
+pub fn render(source: &str, errors: &[DisplayableError]) -> String {
+    use fmt::Write;
+    let mut out = String::new();
+    let lines: Vec<&str> = source.lines().collect();
+    for err in errors {
+        let line_no = err.line as usize;
+        let col = err.column;
+        let source_line = lines.get(line_no.saturating_sub(1)).copied().unwrap_or("");
+        let gutter = line_no.to_string();
+        let pad = " ".repeat(gutter.len());
+
+        let _ = writeln!(out, "error: {}", err.error);
+        let _ = writeln!(out, "{pad}--> {line_no}:{col}");
+        let _ = writeln!(out, "{pad} |");
+        let _ = writeln!(out, "{gutter} | {source_line}");
+        // The caret sits under the 1-based column; underline the failed token.
+        let caret = "^".repeat(err.underline_len());
+        let _ = writeln!(
+            out,
+            "{pad} | {}{caret}",
+            " ".repeat(col.saturating_sub(1))
+        );
+        let _ = writeln!(out, "{pad} = help: {}", err.error);
+        // A concrete fix, when we can guess one.
+        if let Some(suggestion) = &err.error.suggestion {
+            let _ = writeln!(out, "{pad} = suggestion: {suggestion}");
+        }
+        let _ = writeln!(out);
+    }
+    out
+}
+
+pub struct DisplayErr {
+    pub kind: VerboseErrorKind,
+    /// A concrete fix for this error, phrased for the author, when the failing
+    /// parser frame is specific enough to guess one.
+    pub suggestion: Option<String>,
+}
 
 impl From<VerboseErrorKind> for DisplayErr {
-    fn from(value: VerboseErrorKind) -> Self {
-        Self(value)
+    fn from(kind: VerboseErrorKind) -> Self {
+        let suggestion = suggestion_for(&kind);
+        Self { kind, suggestion }
     }
 }
 
 impl From<DisplayErr> for VerboseErrorKind {
     fn from(value: DisplayErr) -> Self {
-        value.0
+        value.kind
     }
 }
 
+/// Guess an actionable fix from the `context` label or expected character that
+/// the failing parser frame carries.
+fn suggestion_for(kind: &VerboseErrorKind) -> Option<String> {
+    let suggestion = match kind {
+        VerboseErrorKind::Context("in to close the let block") => {
+            "expected `in` to close this `let` block"
+        }
+        VerboseErrorKind::Context("type signature close paren )") => {
+            "type signature is missing its closing `)`"
+        }
+        VerboseErrorKind::Context("function invocation") => {
+            "function invocation is missing a closing `)`"
+        }
+        VerboseErrorKind::Char(c) => return Some(format!("expected `{c}` here")),
+        _ => return None,
+    };
+    Some(suggestion.to_owned())
+}
+
 impl fmt::Display for DisplayErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {
+        match self.kind {
             VerboseErrorKind::Context(s) => s.fmt(f),
             VerboseErrorKind::Char(c) => c.fmt(f),
             VerboseErrorKind::Nom(kind) => kind.description().fmt(f),
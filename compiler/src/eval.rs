@@ -0,0 +1,310 @@
+//! Tree-walking evaluator for KCL programs.
+//!
+//! The parser turns source code into an [`AbstractSyntaxTree`], but that tree
+//! cannot be run on its own. This module interprets the body expression of a
+//! function, extending a scope as it descends through `let` bindings and
+//! function calls, so a KCL program actually produces a value.
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{AbstractSyntaxTree, Expression, FnInvocation, Operator};
+
+/// How deeply function calls are allowed to nest before evaluation gives up.
+/// This stops a recursive program from overflowing the host stack.
+const MAX_RECURSION_DEPTH: usize = 256;
+
+/// A value produced by evaluating an expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Value {
+    /// The value as an `f64`, for arithmetic that has been promoted to floating
+    /// point. `None` for values that aren't numbers.
+    fn as_f64(self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(n as f64),
+            Value::Float(x) => Some(x),
+            Value::Bool(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => n.fmt(f),
+            Value::Float(x) => x.fmt(f),
+            Value::Bool(b) => b.fmt(f),
+        }
+    }
+}
+
+/// Something went wrong while evaluating a program.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EvalError {
+    /// A function was requested as the entrypoint, or called, but no `FnDef`
+    /// with that name exists.
+    UnknownFunction(String),
+    /// A `Name` expression referred to an identifier that isn't bound in scope.
+    UnboundName(String),
+    /// A function was called with the wrong number of arguments.
+    ArityMismatch {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A division, integer or floating-point, had a zero divisor.
+    DivideByZero,
+    /// Function calls nested deeper than [`MAX_RECURSION_DEPTH`].
+    RecursionLimitExceeded,
+    /// A non-numeric value (e.g. a string literal) was used where a number was
+    /// expected.
+    NotANumber,
+    /// A non-boolean value was used as the condition of an `if`.
+    NotABoolean,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::UnknownFunction(name) => write!(f, "no function named '{name}'"),
+            EvalError::UnboundName(name) => write!(f, "'{name}' is not bound in this scope"),
+            EvalError::ArityMismatch {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function '{function}' expects {expected} argument(s) but got {found}"
+            ),
+            EvalError::DivideByZero => write!(f, "division by zero"),
+            EvalError::RecursionLimitExceeded => {
+                write!(f, "maximum recursion depth ({MAX_RECURSION_DEPTH}) exceeded")
+            }
+            EvalError::NotANumber => write!(f, "expected a number"),
+            EvalError::NotABoolean => write!(f, "expected a boolean condition"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// A scope maps identifiers to the values currently bound to them.
+/// Each function call and each `let ... in` block evaluates in its own scope.
+type Scope = HashMap<String, Value>;
+
+/// Evaluate `entrypoint` in `ast`, binding its parameters to `args`.
+pub fn eval_program(
+    ast: &AbstractSyntaxTree,
+    entrypoint: &str,
+    args: &[Value],
+) -> Result<Value, EvalError> {
+    call_function(ast, entrypoint, args, 0)
+}
+
+/// Look up a function by name, check its arity, and evaluate its body in a
+/// fresh scope containing only its parameters.
+fn call_function(
+    ast: &AbstractSyntaxTree,
+    name: &str,
+    args: &[Value],
+    depth: usize,
+) -> Result<Value, EvalError> {
+    if depth > MAX_RECURSION_DEPTH {
+        return Err(EvalError::RecursionLimitExceeded);
+    }
+    let func = ast
+        .functions
+        .iter()
+        .find(|f| f.fn_name.to_string() == name)
+        .ok_or_else(|| EvalError::UnknownFunction(name.to_owned()))?;
+    if func.params.len() != args.len() {
+        return Err(EvalError::ArityMismatch {
+            function: name.to_owned(),
+            expected: func.params.len(),
+            found: args.len(),
+        });
+    }
+
+    let mut scope = Scope::new();
+    for (param, value) in func.params.iter().zip(args) {
+        scope.insert(param.name.to_string(), *value);
+    }
+    eval_expr(ast, &func.body, &scope, depth)
+}
+
+/// Evaluate a single expression against `scope`.
+fn eval_expr(
+    ast: &AbstractSyntaxTree,
+    expr: &Expression,
+    scope: &Scope,
+    depth: usize,
+) -> Result<Value, EvalError> {
+    match expr {
+        Expression::Number(n) => Ok(Value::Int(*n as i64)),
+        Expression::Float(x) => Ok(Value::Float(*x)),
+        // String literals aren't numbers, so they can't be evaluated by this
+        // numeric evaluator.
+        Expression::Str(_) => Err(EvalError::NotANumber),
+        Expression::Bool(b) => Ok(Value::Bool(*b)),
+        Expression::Name(ident) => scope
+            .get(&ident.to_string())
+            .copied()
+            .ok_or_else(|| EvalError::UnboundName(ident.to_string())),
+        Expression::Arithmetic { lhs, op, rhs } => {
+            let lhs = eval_expr(ast, lhs, scope, depth)?;
+            let rhs = eval_expr(ast, rhs, scope, depth)?;
+            eval_binop(lhs, *op, rhs)
+        }
+        Expression::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => match eval_expr(ast, cond, scope, depth)? {
+            Value::Bool(true) => eval_expr(ast, then_branch, scope, depth),
+            Value::Bool(false) => eval_expr(ast, else_branch, scope, depth),
+            Value::Int(_) | Value::Float(_) => Err(EvalError::NotABoolean),
+        },
+        Expression::LetIn { r#let, r#in } => {
+            // Bindings are introduced in order, so a later binding can refer to
+            // an earlier one.
+            let mut inner = scope.clone();
+            for assignment in r#let {
+                let value = eval_expr(ast, &assignment.value, &inner, depth)?;
+                inner.insert(assignment.identifier.to_string(), value);
+            }
+            eval_expr(ast, r#in, &inner, depth)
+        }
+        Expression::FnInvocation(FnInvocation { fn_name, args }) => {
+            let evaluated = args
+                .iter()
+                .map(|arg| eval_expr(ast, arg, scope, depth))
+                .collect::<Result<Vec<_>, _>>()?;
+            call_function(ast, &fn_name.to_string(), &evaluated, depth + 1)
+        }
+    }
+}
+
+/// Apply a binary operator to two values. Arithmetic operators produce a
+/// number; comparison operators produce a boolean. Two integers stay integers;
+/// if either operand is a float the operation is promoted to floating point.
+/// Booleans aren't numbers, so they can't be combined.
+fn eval_binop(lhs: Value, op: Operator, rhs: Value) -> Result<Value, EvalError> {
+    match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => eval_int_binop(lhs, op, rhs),
+        _ => {
+            let (Some(lhs), Some(rhs)) = (lhs.as_f64(), rhs.as_f64()) else {
+                return Err(EvalError::NotANumber);
+            };
+            eval_float_binop(lhs, op, rhs)
+        }
+    }
+}
+
+/// Integer arithmetic and comparison.
+fn eval_int_binop(lhs: i64, op: Operator, rhs: i64) -> Result<Value, EvalError> {
+    Ok(match op {
+        Operator::Add => Value::Int(lhs + rhs),
+        Operator::Sub => Value::Int(lhs - rhs),
+        Operator::Mul => Value::Int(lhs * rhs),
+        Operator::Div => {
+            if rhs == 0 {
+                return Err(EvalError::DivideByZero);
+            }
+            Value::Int(lhs / rhs)
+        }
+        Operator::Eq => Value::Bool(lhs == rhs),
+        Operator::Lt => Value::Bool(lhs < rhs),
+        Operator::Gt => Value::Bool(lhs > rhs),
+        Operator::Le => Value::Bool(lhs <= rhs),
+        Operator::Ge => Value::Bool(lhs >= rhs),
+    })
+}
+
+/// Floating-point arithmetic and comparison.
+fn eval_float_binop(lhs: f64, op: Operator, rhs: f64) -> Result<Value, EvalError> {
+    Ok(match op {
+        Operator::Add => Value::Float(lhs + rhs),
+        Operator::Sub => Value::Float(lhs - rhs),
+        Operator::Mul => Value::Float(lhs * rhs),
+        Operator::Div => {
+            if rhs == 0.0 {
+                return Err(EvalError::DivideByZero);
+            }
+            Value::Float(lhs / rhs)
+        }
+        Operator::Eq => Value::Bool(lhs == rhs),
+        Operator::Lt => Value::Bool(lhs < rhs),
+        Operator::Gt => Value::Bool(lhs > rhs),
+        Operator::Le => Value::Bool(lhs <= rhs),
+        Operator::Ge => Value::Bool(lhs >= rhs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `src`, asserting it is free of parse errors, and return the AST.
+    fn program(src: &str) -> AbstractSyntaxTree<'_> {
+        let (ast, diagnostics) = crate::parse(src);
+        assert!(diagnostics.is_empty(), "test program did not parse: {src}");
+        ast
+    }
+
+    #[test]
+    fn evaluates_arithmetic_body() {
+        let ast = program("area = ( -> Int) => (2 + 3 * 4)");
+        assert_eq!(eval_program(&ast, "area", &[]), Ok(Value::Int(14)));
+    }
+
+    #[test]
+    fn unknown_entrypoint_is_reported() {
+        let ast = program("area = ( -> Int) => 1");
+        assert_eq!(
+            eval_program(&ast, "missing", &[]),
+            Err(EvalError::UnknownFunction("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn unbound_name_is_reported() {
+        let ast = program("area = ( -> Int) => x");
+        assert_eq!(
+            eval_program(&ast, "area", &[]),
+            Err(EvalError::UnboundName("x".to_owned()))
+        );
+    }
+
+    #[test]
+    fn arity_mismatch_is_reported() {
+        let ast = program("area = (r: Int -> Int) => r");
+        assert_eq!(
+            eval_program(&ast, "area", &[]),
+            Err(EvalError::ArityMismatch {
+                function: "area".to_owned(),
+                expected: 1,
+                found: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn division_by_zero_is_reported() {
+        let ast = program("area = ( -> Int) => (1 / 0)");
+        assert_eq!(eval_program(&ast, "area", &[]), Err(EvalError::DivideByZero));
+    }
+
+    #[test]
+    fn runaway_recursion_is_bounded() {
+        let ast = program("spin = ( -> Int) => spin()");
+        assert_eq!(
+            eval_program(&ast, "spin", &[]),
+            Err(EvalError::RecursionLimitExceeded)
+        );
+    }
+}
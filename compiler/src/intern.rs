@@ -0,0 +1,64 @@
+//! String interning for identifiers.
+//!
+//! Identifiers start life as borrowed source slices, which makes equality a
+//! string compare and makes names awkward to carry through later passes.
+//! Interning maps each distinct identifier text to a small, copyable
+//! [`Symbol`], so the type-checker and evaluator can compare names in O(1) and
+//! the whole program shares a single identifier table (as rustc's
+//! `syntax::symbol` and rust-analyzer do). The [`Interner`] is owned by a
+//! compilation session; the original span stays on the `Identifier` for
+//! diagnostics.
+use std::collections::HashMap;
+
+/// A copyable handle to an interned string. Cheap to pass around and compare.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Symbol(u32);
+
+/// Maps identifier text to [`Symbol`]s and back.
+#[derive(Debug, Default)]
+pub struct Interner {
+    lookup: HashMap<String, Symbol>,
+    strings: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intern `text`, returning its symbol. Interning the same text twice
+    /// returns the same symbol.
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(&sym) = self.lookup.get(text) {
+            return sym;
+        }
+        let sym = Symbol(self.strings.len() as u32);
+        self.strings.push(text.to_owned());
+        self.lookup.insert(text.to_owned(), sym);
+        sym
+    }
+
+    /// Recover the text a symbol stands for, for rendering.
+    ///
+    /// Panics if `sym` was not produced by this interner.
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        &self.strings[sym.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_is_stable_and_reversible() {
+        let mut interner = Interner::new();
+        let radius = interner.intern("radius");
+        let center = interner.intern("center");
+        // Re-interning the same text yields the same symbol.
+        assert_eq!(radius, interner.intern("radius"));
+        assert_ne!(radius, center);
+        assert_eq!(interner.resolve(radius), "radius");
+        assert_eq!(interner.resolve(center), "center");
+    }
+}
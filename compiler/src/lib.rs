@@ -1,25 +1,25 @@
 mod ast;
+pub mod codegen_hvm;
 pub mod displayable_error;
+pub mod eval;
+pub mod intern;
+pub mod lossless;
 mod parser;
 pub mod semantics;
 
 pub use ast::AbstractSyntaxTree;
-use displayable_error::DisplayableError;
-use nom::Finish;
-use parser::{Input, Parser};
+use displayable_error::Diagnostics;
+use parser::Input;
 
-/// Parse the AST.
-/// If successful, returns the remaining unparsed input and the AST.
-/// If error, return the "parser tree trace", i.e. a the stack trace of all parsers which
-/// were attempting to parse when the deepest one failed. Ordered from deepest to root.
-pub fn parse(
-    source_code: &str,
-) -> Result<(parser::Input, ast::AbstractSyntaxTree), Vec<DisplayableError>> {
-    let input = Input::new(source_code);
-    AbstractSyntaxTree::parse(input).finish().map_err(|e| {
-        e.errors
-            .into_iter()
-            .map(|(input, e)| DisplayableError::new(input, e))
-            .collect()
-    })
+/// Parse a whole KCL program.
+///
+/// A program is a series of function definitions. Parsing is error-resilient:
+/// rather than bailing at the first syntax error, a failed function definition
+/// is recorded as a diagnostic and parsing resynchronises to the next plausible
+/// top-level `fn` start before continuing. The result is therefore a
+/// (possibly partial) [`AbstractSyntaxTree`] holding every function that did
+/// parse, together with a diagnostic for every independent error found in the
+/// one pass, so the CLI can show all problems at once.
+pub fn parse(source_code: &str) -> (AbstractSyntaxTree<'_>, Diagnostics<'_>) {
+    AbstractSyntaxTree::parse_recovering(Input::new(source_code))
 }
@@ -1,29 +1,197 @@
 //! Implements the Parser trait for all the AST types.
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{self as character, char as one_char},
-    combinator::{all_consuming, map, map_res, recognize},
-    error::context,
+    bytes::complete::{escaped_transform, tag, take_while},
+    character::complete::{self as character, char as one_char, none_of, satisfy},
+    combinator::{all_consuming, cut, map, map_res, not, opt, recognize, value},
+    error::{context, ErrorKind, ParseError, VerboseError},
     multi::{many0, many1, separated_list0},
-    sequence::{delimited, preceded, separated_pair, terminated, tuple},
+    sequence::{delimited, pair, separated_pair, terminated, tuple},
+    Slice,
 };
 
+use crate::displayable_error::{Diagnostics, DisplayableError};
 use crate::{ast::*, parser::*};
 
 /// These can't be used as names in KCL programs.
-const RESERVED_KEYWORDS: [&str; 2] = ["let", "in"];
+const RESERVED_KEYWORDS: [&str; 7] = ["let", "in", "if", "then", "else", "true", "false"];
 
 impl<'i> Parser<'i> for AbstractSyntaxTree<'i> {
     fn parse(i: Input<'i>) -> Result<Self> {
-        let parser = all_consuming(many0(FnDef::parse));
+        let parser = all_consuming(terminated(many0(FnDef::parse), skip_trivia));
         context(
             "program root",
-            map(parser, |functions| AbstractSyntaxTree { functions }),
+            map(parser, |mut functions| {
+                number_functions(&mut functions);
+                AbstractSyntaxTree { functions }
+            }),
         )(i)
     }
 }
 
+/// Assign each function a unique [`NodeId`] by its position in the file. The id
+/// is parse-time identity only, so numbering in source order keeps it stable
+/// and unique without threading a generator through every combinator.
+fn number_functions(functions: &mut [FnDef<'_>]) {
+    for (idx, func) in functions.iter_mut().enumerate() {
+        func.node_id = Some(NodeId(idx as u32));
+    }
+}
+
+impl<'i> AbstractSyntaxTree<'i> {
+    /// Parse a whole program, recovering from errors instead of bailing at the
+    /// first one. When a function definition fails to parse, the `nom_locate`
+    /// span (byte offset, line and column) of the deepest `VerboseError` frame
+    /// is recorded as a diagnostic together with the innermost `context` label
+    /// wrapped around that failure, then parsing resynchronises to the next plausible top-level
+    /// function start and continues. The result is whatever functions did
+    /// parse, plus one diagnostic per independent error found in the pass.
+    pub fn parse_recovering(i: Input<'i>) -> (Self, Diagnostics<'i>) {
+        let mut i = i;
+        let mut functions = Vec::new();
+        let mut errors = Diagnostics::new();
+        loop {
+            let Ok((rest, ())) = skip_trivia(i) else { break };
+            i = rest;
+            if i.fragment().is_empty() {
+                break;
+            }
+            match FnDef::parse(i) {
+                Ok((rest, func)) => {
+                    functions.push(func);
+                    i = rest;
+                }
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                    // The deepest frame locates the failure; the first context
+                    // frame after it names what was being parsed there.
+                    let label = e
+                        .errors
+                        .iter()
+                        .find(|(_, kind)| matches!(kind, nom::error::VerboseErrorKind::Context(_)))
+                        .map(|(_, kind)| kind.clone());
+                    if let Some((err_input, kind)) = e.errors.into_iter().next() {
+                        errors.push(DisplayableError::new(err_input, label.unwrap_or(kind)));
+                    }
+                    let recovered = resync_to_next_fn(i);
+                    // Guarantee forward progress so recovery can't loop forever.
+                    i = if recovered.location_offset() > i.location_offset() {
+                        recovered
+                    } else {
+                        i.slice(i.fragment().len()..)
+                    };
+                }
+                Err(nom::Err::Incomplete(_)) => break,
+            }
+        }
+        number_functions(&mut functions);
+        (AbstractSyntaxTree { functions }, errors)
+    }
+}
+
+/// Skip forward to the start of the next line that could begin a new top-level
+/// function definition (a non-indented identifier), or to end-of-input if there
+/// is none.
+fn resync_to_next_fn(i: Input<'_>) -> Input<'_> {
+    let fragment = i.fragment();
+    let mut offset = 0;
+    while let Some(next_newline) = fragment[offset..].find('\n') {
+        offset += next_newline + 1;
+        match fragment[offset..].chars().next() {
+            // A top-level definition starts in the first column with a letter
+            // or an underscore, as an identifier may.
+            Some(c) if c.is_alphabetic() || c == '_' => return i.slice(offset..),
+            _ => {}
+        }
+    }
+    i.slice(fragment.len()..)
+}
+
+/// Wrap a parser so it skips any leading insignificant trivia before running.
+/// Structural punctuation is matched through this combinator rather than
+/// space-padded literal tags, so the grammar tolerates arbitrary inter-token
+/// whitespace, indentation and comments.
+fn tok<'i, O, F>(mut inner: F) -> impl FnMut(Input<'i>) -> Result<'i, O>
+where
+    F: FnMut(Input<'i>) -> Result<'i, O>,
+{
+    move |i| {
+        let (i, _) = skip_trivia(i)?;
+        inner(i)
+    }
+}
+
+/// Match the keyword `kw`, but only when it stands on its own rather than as
+/// the prefix of a longer identifier. Without the trailing word-boundary check,
+/// `tag("let")` would also fire on `letter` and `tag("true")` on `trueish`,
+/// swallowing the keyword and leaving a stray identifier tail behind.
+fn keyword<'i>(kw: &'static str) -> impl FnMut(Input<'i>) -> Result<'i, Input<'i>> {
+    terminated(tag(kw), not(satisfy(|c: char| c.is_alphanumeric() || c == '_')))
+}
+
+/// Consume insignificant trivia: whitespace, `//` line comments, and (possibly
+/// nested) `/* ... */` block comments. Comments are legal anywhere whitespace
+/// is, so this is folded into [`tok`] and produces no AST nodes.
+fn skip_trivia(i: Input) -> Result<()> {
+    let mut i = i;
+    loop {
+        let (rest, _) = character::multispace0::<Input, VerboseError<Input>>(i)?;
+        if let Ok((after, ())) = line_comment(rest) {
+            i = after;
+            continue;
+        }
+        if let Ok((after, ())) = block_comment(rest) {
+            i = after;
+            continue;
+        }
+        return Ok((rest, ()));
+    }
+}
+
+/// A `//` comment, running to (but not consuming) the end of the line.
+fn line_comment(i: Input) -> Result<()> {
+    if !i.fragment().starts_with("//") {
+        return Err(nom::Err::Error(VerboseError::from_error_kind(
+            i,
+            ErrorKind::Tag,
+        )));
+    }
+    let len = i.fragment().find('\n').unwrap_or(i.fragment().len());
+    Ok((i.slice(len..), ()))
+}
+
+/// A `/* ... */` block comment. Nesting is supported, so the first `*/` does
+/// not necessarily close an outer comment.
+fn block_comment(i: Input) -> Result<()> {
+    if !i.fragment().starts_with("/*") {
+        return Err(nom::Err::Error(VerboseError::from_error_kind(
+            i,
+            ErrorKind::Tag,
+        )));
+    }
+    let mut i = i.slice(2..);
+    let mut depth = 1usize;
+    while depth > 0 {
+        let fragment = i.fragment();
+        if fragment.is_empty() {
+            // Unterminated block comment.
+            return Err(nom::Err::Error(VerboseError::from_error_kind(
+                i,
+                ErrorKind::TakeUntil,
+            )));
+        } else if fragment.starts_with("/*") {
+            depth += 1;
+            i = i.slice(2..);
+        } else if fragment.starts_with("*/") {
+            depth -= 1;
+            i = i.slice(2..);
+        } else {
+            let char_len = fragment.chars().next().expect("fragment is non-empty").len_utf8();
+            i = i.slice(char_len..);
+        }
+    }
+    Ok((i, ()))
+}
+
 impl<'i> Parser<'i> for Identifier<'i> {
     fn parse(i: Input<'i>) -> Result<'i, Self> {
         // Checks if the ID is in the reserved keyword list.
@@ -46,11 +214,13 @@ impl<'i> Parser<'i> for Identifier<'i> {
 impl<'i> Identifier<'i> {
     /// Like `Identifier::parse` except it doesn't check if the identifier is a reserved keyword.
     fn parse_maybe_reserved(i: Input<'i>) -> Result<Self> {
-        let parser = preceded(
-            // Identifiers cannot start with a number
-            nom_unicode::complete::alpha1,
-            // But after the first char, they can include numbers.
-            nom_unicode::complete::alphanumeric0,
+        let parser = pair(
+            // The first character must be a letter or an underscore, never a
+            // digit.
+            satisfy(|c| c.is_alphabetic() || c == '_'),
+            // After the first character, identifiers may also contain digits and
+            // underscores (as schala's `alphanumeric0`-plus-underscore lexing does).
+            take_while(|c: char| c.is_alphanumeric() || c == '_'),
         );
         map(recognize(parser), Self)(i)
     }
@@ -60,36 +230,41 @@ impl<'i> Parser<'i> for FnDef<'i> {
     /// FnDef looks like
     ///     myCircle = (radius: Distance -> Solid2D) => circle(radius)
     fn parse(i: Input<'i>) -> Result<Self> {
+        // The node id is assigned by position once the whole program has
+        // parsed (see `number_functions`); a freshly parsed definition carries
+        // none yet.
+
         // Parse the parts of a function definition.
         let parse_parts = tuple((
-            context("function name", Identifier::parse),
-            context("= between function name and definition", tag(" = ")),
-            context(
-                "type signature",
-                bracketed(tuple((
-                    context(
-                        "parameter list",
-                        separated_list0(tag(", "), Parameter::parse),
-                    ),
-                    context("return type arrow ->", tag(" -> ")),
-                    Identifier::parse,
-                ))),
-            ),
-            context(
-                "=> between function header and body",
-                terminated(tag(" =>"), character::multispace0),
-            ),
-            context("function body", Expression::parse),
+            context("function name", tok(Identifier::parse)),
+            context("= between function name and definition", tok(one_char('='))),
+            context("type signature open paren (", tok(one_char('('))),
+            // Having matched `name = (`, we're unambiguously inside a function
+            // definition. Commit with `cut` so any later error is reported at
+            // its real site instead of backtracking out to whatever `alt` or
+            // `many0` called us and surfacing as a misleading frame.
+            cut(tuple((
+                context(
+                    "parameter list",
+                    separated_list0(tok(one_char(',')), Parameter::parse),
+                ),
+                context("return type arrow ->", tok(tag("->"))),
+                tok(Identifier::parse),
+                context("type signature close paren )", tok(one_char(')'))),
+                context("=> between function header and body", tok(tag("=>"))),
+                context("function body", Expression::parse),
+            ))),
         ));
 
         // Convert the parts we actually need into a FnDef, ignoring the parts we don't need.
         let parser = map(
             parse_parts,
-            |(fn_name, _, (params, _, return_type), _, body)| Self {
+            |(fn_name, _, _, (params, _, return_type, _, _, body))| Self {
                 fn_name,
                 params,
                 return_type,
                 body,
+                node_id: None,
             },
         );
         context("function definition", parser)(i)
@@ -100,7 +275,7 @@ impl<'i> Parser<'i> for Parameter<'i> {
     fn parse(i: Input<'i>) -> Result<Self> {
         // Looks like `radius: Distance`
         let parser = map(
-            separated_pair(Identifier::parse, tag(": "), Identifier::parse),
+            separated_pair(tok(Identifier::parse), tok(one_char(':')), tok(Identifier::parse)),
             |(name, kcl_type)| Self { name, kcl_type },
         );
         context("parameter", parser)(i)
@@ -111,9 +286,9 @@ impl<'i> Parser<'i> for FnInvocation<'i> {
     fn parse(i: Input<'i>) -> Result<Self> {
         let parse_parts = tuple((
             Identifier::parse,
-            one_char('('),
-            separated_list0(tag(", "), Expression::parse),
-            one_char(')'),
+            tok(one_char('(')),
+            separated_list0(tok(one_char(',')), Expression::parse),
+            tok(one_char(')')),
         ));
         let parser = map(parse_parts, |(fn_name, _, args, _)| FnInvocation {
             fn_name,
@@ -125,89 +300,230 @@ impl<'i> Parser<'i> for FnInvocation<'i> {
 
 impl<'i> Parser<'i> for Expression<'i> {
     fn parse(i: Input<'i>) -> Result<Self> {
-        let parser = alt((
-            Self::parse_arithmetic,
-            Self::parse_num,
-            Self::parse_let_in,
-            map(FnInvocation::parse, Self::FnInvocation),
-            map(Identifier::parse, Self::Name),
-        ));
-        context("expression", parser)(i)
+        context("expression", |i| Self::parse_expr_bp(i, 0))(i)
     }
 }
 
 impl<'i> Parser<'i> for Operator {
     fn parse(i: Input<'i>) -> Result<Self> {
-        let parser = map_res(
-            alt((one_char('+'), one_char('-'), one_char('*'), one_char('/'))),
-            |symbol| {
-                Ok(match dbg!(symbol) {
-                    '+' => Self::Add,
-                    '-' => Self::Sub,
-                    '*' => Self::Mul,
-                    '/' => Self::Div,
-                    other => return Err(format!("Invalid operator {other}")),
-                })
-            },
-        );
+        // Try multi-character operators before their single-character prefixes
+        // so `<=` doesn't parse as `<`.
+        let symbol = tok(alt((
+            tag("=="),
+            tag("<="),
+            tag(">="),
+            tag("+"),
+            tag("-"),
+            tag("*"),
+            tag("/"),
+            tag("<"),
+            tag(">"),
+        )));
+        let parser = map_res(symbol, |symbol: Input| {
+            Ok(match *symbol.fragment() {
+                "+" => Self::Add,
+                "-" => Self::Sub,
+                "*" => Self::Mul,
+                "/" => Self::Div,
+                "==" => Self::Eq,
+                "<" => Self::Lt,
+                ">" => Self::Gt,
+                "<=" => Self::Le,
+                ">=" => Self::Ge,
+                other => return Err(format!("Invalid operator {other}")),
+            })
+        });
         context("operator", parser)(i)
     }
 }
 
+/// The binding power (precedence) of a binary operator. `*` and `/` bind more
+/// tightly than `+` and `-`, so `1 + 2 * 3` parses as `1 + (2 * 3)`.
+fn binding_power(op: Operator) -> u8 {
+    match op {
+        Operator::Eq
+        | Operator::Lt
+        | Operator::Gt
+        | Operator::Le
+        | Operator::Ge => 1,
+        Operator::Add | Operator::Sub => 2,
+        Operator::Mul | Operator::Div => 3,
+    }
+}
+
 impl<'i> Expression<'i> {
-    fn parse_arithmetic(i: Input<'i>) -> Result<Self> {
-        let parser = map(
-            bracketed(tuple((
-                Self::parse,
-                delimited(one_char(' '), Operator::parse, one_char(' ')),
-                Self::parse,
-            ))),
-            |(lhs, op, rhs)| Self::Arithmetic {
+    /// Precedence-climbing expression parser. Parses a primary expression, then
+    /// folds in any following `op rhs` pairs whose operator binds at least as
+    /// tightly as `min_prec`. The right-hand side is parsed with `op_prec + 1`,
+    /// giving left-associativity.
+    fn parse_expr_bp(i: Input<'i>, min_prec: u8) -> Result<'i, Self> {
+        let (mut i, mut lhs) = Self::parse_primary(i)?;
+        loop {
+            // Peek the next operator without committing; if there isn't one, or
+            // it binds less tightly than we're allowed to consume, stop here.
+            let Ok((after_op, op)) = Operator::parse(i) else {
+                break;
+            };
+            if binding_power(op) < min_prec {
+                break;
+            }
+            let (rest, rhs) = Self::parse_expr_bp(after_op, binding_power(op) + 1)?;
+            i = rest;
+            lhs = Self::Arithmetic {
                 lhs: Box::new(lhs),
                 op,
                 rhs: Box::new(rhs),
-            },
+            };
+        }
+        Ok((i, lhs))
+    }
+
+    /// A primary expression: a number, string, boolean, `if`, let-in, function
+    /// invocation, name, or a parenthesised sub-expression. Parentheses reset precedence, so any
+    /// expression can appear inside them.
+    fn parse_primary(i: Input<'i>) -> Result<Self> {
+        // Both parens go through `tok`, like `FnInvocation`, so trivia may
+        // surround the grouped expression, e.g. `( 1 + 2 )` or a trailing
+        // comment before the closing paren.
+        let grouped = map(
+            tuple((
+                tok(one_char('(')),
+                |i| Self::parse_expr_bp(i, 0),
+                context("close paren ) of grouped expression", tok(one_char(')'))),
+            )),
+            |(_, expr, _)| expr,
         );
-        context("arithmetic", parser)(i)
+        let parser = tok(alt((
+            Self::parse_num,
+            Self::parse_str,
+            Self::parse_bool,
+            Self::parse_if,
+            Self::parse_let_in,
+            map(FnInvocation::parse, Self::FnInvocation),
+            map(Identifier::parse, Self::Name),
+            grouped,
+        )));
+        context("primary expression", parser)(i)
     }
 
     fn parse_let_in(i: Input<'i>) -> Result<Self> {
+        // `let` binds one or more assignments before `in`. Each piece skips its
+        // own leading trivia, so assignments can be laid out however the author
+        // likes and may carry comments. `many1` stops at `in`, because `in` is
+        // a reserved keyword and so fails `Assignment::parse`.
         let parser = map(
             tuple((
-                tag("let"),
-                character::newline,
-                many1(tuple((
-                    character::multispace0,
-                    Assignment::parse,
-                    character::newline,
+                // Only a complete `let` keyword (not the prefix of an
+                // identifier like `letter`) commits us to a let-in block; once
+                // past it the bindings and closing `in` must follow, so cut
+                // rather than backtrack.
+                tok(keyword("let")),
+                cut(tuple((
+                    many1(Assignment::parse),
+                    context("in to close the let block", tok(keyword("in"))),
+                    Expression::parse,
                 ))),
-                terminated(
-                    preceded(character::multispace0, tag("in")),
-                    character::multispace0,
-                ),
-                Expression::parse,
             )),
-            |(_, _, assignments, _, expr)| Self::LetIn {
-                r#let: assignments
-                    .into_iter()
-                    .map(|(_, assign, _)| assign)
-                    .collect(),
+            |(_, (assignments, _, expr))| Self::LetIn {
+                r#let: assignments,
                 r#in: Box::new(expr),
             },
         );
         context("let-in", parser)(i)
     }
 
+    fn parse_bool(i: Input<'i>) -> Result<Self> {
+        let parser = alt((
+            value(Self::Bool(true), keyword("true")),
+            value(Self::Bool(false), keyword("false")),
+        ));
+        context("boolean", parser)(i)
+    }
+
+    fn parse_if(i: Input<'i>) -> Result<Self> {
+        // `if <cond> then <then> else <else>`.
+        let parser = map(
+            tuple((
+                tok(keyword("if")),
+                Expression::parse,
+                tok(keyword("then")),
+                Expression::parse,
+                tok(keyword("else")),
+                Expression::parse,
+            )),
+            |(_, cond, _, then_branch, _, else_branch)| Self::If {
+                cond: Box::new(cond),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            },
+        );
+        context("if expression", parser)(i)
+    }
+
+    fn parse_str(i: Input<'i>) -> Result<Self> {
+        // A double-quoted literal, decoding the supported escape sequences.
+        // `opt` lets an empty literal `""` parse to an empty string.
+        let unescape = escaped_transform(
+            none_of("\\\""),
+            '\\',
+            alt((
+                value("\n", one_char('n')),
+                value("\t", one_char('t')),
+                value("\"", one_char('"')),
+                value("\\", one_char('\\')),
+            )),
+        );
+        let parser = map(
+            delimited(one_char('"'), opt(unescape), one_char('"')),
+            |contents| Self::Str(contents.unwrap_or_default()),
+        );
+        context("string literal", parser)(i)
+    }
+
     fn parse_num(i: Input<'i>) -> Result<Self> {
-        // Numbers are a sequence of digits and underscores.
-        let allowed_chars = character::one_of("0123456789_");
-        let number = nom::multi::many1(allowed_chars);
-        let parser = map_res(number, |chars| {
-            let digits_only = chars
-                .into_iter()
-                .filter(|c| c.is_ascii_digit())
-                .collect::<String>();
-            digits_only.parse().map(Self::Number)
+        // A run of digits, allowing `_` separators anywhere within a group.
+        let digits = || recognize(many1(character::one_of("0123456789_")));
+        // A numeric literal: an optional sign, an integer part, an optional
+        // `.fraction`, and an optional `e`/`E` exponent. A leading sign is only
+        // consumed here, in primary position; between two operands the Pratt
+        // loop has already taken any `-`/`+` as an operator, so it never reaches
+        // this parser. A bare `.` has no integer part and so isn't a number; a
+        // trailing `.` (as in `3.`) leaves the dot unconsumed and parses as the
+        // integer alone.
+        let parts = tuple((
+            opt(character::one_of("+-")),
+            digits(),
+            opt(recognize(pair(one_char('.'), digits()))),
+            opt(recognize(tuple((
+                character::one_of("eE"),
+                opt(character::one_of("+-")),
+                digits(),
+            )))),
+        ));
+        let parser = map_res(parts, |(sign, int, frac, exp)| {
+            let strip = |s: &str| s.chars().filter(|c| *c != '_').collect::<String>();
+            // Anything with a fraction, an exponent or a negative sign has to be
+            // a float; a plain run of digits stays an integer.
+            let is_float = frac.is_some() || exp.is_some() || sign == Some('-');
+            if is_float {
+                let mut text = String::new();
+                if let Some(sign) = sign {
+                    text.push(sign);
+                }
+                text.push_str(&strip(int.fragment()));
+                if let Some(frac) = &frac {
+                    text.push_str(&strip(frac.fragment()));
+                }
+                if let Some(exp) = &exp {
+                    text.push_str(&strip(exp.fragment()));
+                }
+                text.parse::<f64>().map(Self::Float).map_err(|e| e.to_string())
+            } else {
+                strip(int.fragment())
+                    .parse::<u64>()
+                    .map(Self::Number)
+                    .map_err(|e| e.to_string())
+            }
         });
         context("number", parser)(i)
     }
@@ -216,8 +532,8 @@ impl<'i> Expression<'i> {
 impl<'i> Parser<'i> for Assignment<'i> {
     fn parse(i: Input<'i>) -> Result<Self> {
         let parts = tuple((
-            Identifier::parse,
-            nom::bytes::complete::tag(" = "),
+            tok(Identifier::parse),
+            tok(one_char('=')),
             Expression::parse,
         ));
         let parser = map(parts, |(identifier, _, value)| Self { identifier, value });
@@ -246,8 +562,6 @@ where
 #[cfg(test)]
 mod tests {
 
-    use tabled::Table;
-
     use super::*;
     use crate::displayable_error::DisplayableError;
 
@@ -264,12 +578,10 @@ mod tests {
                     eprintln!("Could not parse the test case.");
                     eprintln!("Here's the error chain. Top row is the last parser tried, i.e. the bottom of the parse tree.");
                     eprintln!("The bottom row is the root of the parse tree.");
-                    let err_table = Table::new(
-                        e.errors
-                            .into_iter()
-                            .map(|(input, e)| DisplayableError::new(input, e)),
-                    );
-                    eprintln!("{err_table}");
+                    for (input, e) in e.errors {
+                        let err = DisplayableError::new(input, e);
+                        eprintln!("{}:{}: {}", err.line, err.column, err.error);
+                    }
                     panic!("Could not parse test case");
                 }
                 Err(nom::Err::Incomplete(_)) => {
@@ -305,6 +617,28 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_expr_float() {
+        assert_parse(vec![
+            (Expression::Float(3.5), Input::new("3.5")),
+            (Expression::Float(0.25), Input::new("0.25")),
+            // A leading sign in primary position is part of the literal.
+            (Expression::Float(-1.5), Input::new("-1.5")),
+            // Scientific notation, with and without a fractional part.
+            (Expression::Float(1000.0), Input::new("1e3")),
+            (Expression::Float(0.025), Input::new("2.5e-2")),
+            // Underscore separators are allowed in every digit group.
+            (Expression::Float(1000.5), Input::new("1_000.5")),
+        ]);
+        // A bare `.` is not a number.
+        assert_not_parse::<Expression>(Input::new("."));
+        // A trailing `.` is handled consistently: the dot is not consumed, so
+        // the integer parses alone and the `.` is left over.
+        let (rest, parsed) = Expression::parse(Input::new("3.")).unwrap();
+        assert_eq!(parsed, Expression::Number(3));
+        assert_eq!(*rest.fragment(), ".");
+    }
+
     #[test]
     fn test_expr_arith() {
         assert_parse(vec![
@@ -316,10 +650,143 @@ mod tests {
                 },
                 Input::new("(1 + 2)"),
             ),
+            // Whitespace may pad the inside of the parentheses, including right
+            // before the closing paren.
+            (
+                Expression::Arithmetic {
+                    lhs: Box::new(Expression::Number(1)),
+                    op: Operator::Add,
+                    rhs: Box::new(Expression::Number(2)),
+                },
+                Input::new("( 1 + 2 )"),
+            ),
             (Expression::Number(123), Input::new("123")),
         ]);
     }
 
+    #[test]
+    fn test_expr_precedence() {
+        assert_parse(vec![
+            // `*` binds tighter than `+`, so this is `1 + (2 * 3)`.
+            (
+                Expression::Arithmetic {
+                    lhs: Box::new(Expression::Number(1)),
+                    op: Operator::Add,
+                    rhs: Box::new(Expression::Arithmetic {
+                        lhs: Box::new(Expression::Number(2)),
+                        op: Operator::Mul,
+                        rhs: Box::new(Expression::Number(3)),
+                    }),
+                },
+                Input::new("1 + 2 * 3"),
+            ),
+            // `-` is left-associative, so this is `(5 - 2) - 1`.
+            (
+                Expression::Arithmetic {
+                    lhs: Box::new(Expression::Arithmetic {
+                        lhs: Box::new(Expression::Number(5)),
+                        op: Operator::Sub,
+                        rhs: Box::new(Expression::Number(2)),
+                    }),
+                    op: Operator::Sub,
+                    rhs: Box::new(Expression::Number(1)),
+                },
+                Input::new("5 - 2 - 1"),
+            ),
+            // `*` and `/` both bind tighter than `+`, so this is
+            // `(a * b) + (c / d)` with no brackets written.
+            (
+                Expression::Arithmetic {
+                    lhs: Box::new(Expression::Arithmetic {
+                        lhs: Box::new(Expression::Name(Identifier::from_span("a", 0, 1))),
+                        op: Operator::Mul,
+                        rhs: Box::new(Expression::Name(Identifier::from_span("b", 4, 1))),
+                    }),
+                    op: Operator::Add,
+                    rhs: Box::new(Expression::Arithmetic {
+                        lhs: Box::new(Expression::Name(Identifier::from_span("c", 8, 1))),
+                        op: Operator::Div,
+                        rhs: Box::new(Expression::Name(Identifier::from_span("d", 12, 1))),
+                    }),
+                },
+                Input::new("a * b + c / d"),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_expr_string() {
+        assert_parse(vec![
+            (Expression::Str(String::new()), Input::new(r#""""#)),
+            (Expression::Str("hello".to_owned()), Input::new(r#""hello""#)),
+            (
+                Expression::Str("a\tb\nc\"\\".to_owned()),
+                Input::new(r#""a\tb\nc\"\\""#),
+            ),
+            // A string literal passed as a call argument, e.g. a filename.
+            (
+                Expression::FnInvocation(FnInvocation {
+                    fn_name: Identifier::from_span("export", 0, 1),
+                    args: vec![Expression::Str("part.step".to_owned())],
+                }),
+                Input::new(r#"export("part.step")"#),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_expr_bool() {
+        assert_parse(vec![
+            (Expression::Bool(true), Input::new("true")),
+            (Expression::Bool(false), Input::new("false")),
+            // An identifier that merely starts with a keyword is a name, not a
+            // boolean with a stray tail.
+            (
+                Expression::Name(Identifier::from_span("trueish", 0, 1)),
+                Input::new("trueish"),
+            ),
+            (
+                Expression::Name(Identifier::from_span("falseStart", 0, 1)),
+                Input::new("falseStart"),
+            ),
+        ]);
+        // `true` and `false` are reserved and can't be rebound as names.
+        assert_not_parse::<Assignment>(Input::new("true = 1"));
+        assert_not_parse::<Assignment>(Input::new("false = 1"));
+    }
+
+    #[test]
+    fn test_if_in_let() {
+        // A nested conditional as the body of a `let ... in`.
+        assert_parse(vec![(
+            Expression::LetIn {
+                r#let: vec![Assignment {
+                    identifier: Identifier::from_span("x", 8, 2),
+                    value: Expression::Number(1),
+                }],
+                r#in: Box::new(Expression::If {
+                    cond: Box::new(Expression::Arithmetic {
+                        lhs: Box::new(Expression::Name(Identifier::from_span("x", 20, 3))),
+                        op: Operator::Gt,
+                        rhs: Box::new(Expression::Number(0)),
+                    }),
+                    then_branch: Box::new(Expression::Bool(true)),
+                    else_branch: Box::new(Expression::If {
+                        cond: Box::new(Expression::Bool(false)),
+                        then_branch: Box::new(Expression::Number(1)),
+                        else_branch: Box::new(Expression::Number(2)),
+                    }),
+                }),
+            },
+            Input::new("let\n    x = 1\nin if x > 0 then true else if false then 1 else 2"),
+        )]);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        assert_not_parse::<Expression>(Input::new(r#""no closing quote"#));
+    }
+
     #[test]
     fn valid_function_invocations() {
         assert_parse(vec![(
@@ -331,6 +798,25 @@ mod tests {
         )])
     }
 
+    #[test]
+    fn tolerates_irregular_spacing_and_comments() {
+        let expected = || {
+            Expression::FnInvocation(FnInvocation {
+                fn_name: Identifier::from_span("sphere", 0, 1),
+                args: vec![Expression::Number(1), Expression::Number(2)],
+            })
+        };
+        assert_parse(vec![
+            // Missing spaces around the comma, stray spaces inside the parens.
+            (expected(), Input::new("sphere( 1,2 )")),
+            // Arguments split across lines with a line comment and a block comment.
+            (
+                expected(),
+                Input::new("sphere(\n    1, // radius\n    2 /* height */\n)"),
+            ),
+        ]);
+    }
+
     #[test]
     fn valid_function_definition() {
         assert_parse(vec![
@@ -352,6 +838,7 @@ mod tests {
                         args: vec![Expression::Name(Identifier::from_span("radius", 69, 1))],
                     }),
                     return_type: Identifier::from_span("Solid2D", 50, 1),
+                    node_id: None,
                 },
                 Input::new(
                     r#"bigCircle = (radius: Distance, center: Point2D -> Solid2D) => circle(radius)"#,
@@ -382,6 +869,7 @@ mod tests {
                         })),
                     },
                     return_type: Identifier::from_span("Solid2D", 32, 1),
+                    node_id: None,
                 },
                 Input::new(
                     "\
@@ -447,16 +935,18 @@ in y"#,
 
     #[test]
     fn test_assignment() {
-        let valid_lhs = ["n"];
+        let valid_lhs = ["n", "n_hello", "_private", "a_b_c2"];
         let tests: Vec<_> = valid_lhs
             .into_iter()
             .flat_map(|lhs| {
+                // `foo` starts after the left-hand side, a space, `=` and a space.
+                let fn_offset = lhs.len() + 3;
                 vec![
                     (
                         Assignment {
                             identifier: Identifier::from_span(lhs, 0, 1),
                             value: Expression::FnInvocation(FnInvocation {
-                                fn_name: Identifier::from_span("foo", 4, 1),
+                                fn_name: Identifier::from_span("foo", fn_offset, 1),
                                 args: vec![Expression::Number(100)],
                             }),
                         },
@@ -489,8 +979,6 @@ in y"#,
             "let",
             "in",
             "0000000aassdfasdfasdfasdf013423452342134234234234",
-            // TODO: fix this, it should be valid.
-            "n_hello",
         ];
         for identifier in invalid_binding_names {
             let i = format!("{identifier} = 100");
@@ -1,5 +1,18 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Expression, FnInvocation, Identifier, Operator};
 use crate::AbstractSyntaxTree;
 
+/// Whether an operator is a comparison (yielding a boolean) rather than
+/// arithmetic (yielding the operand type).
+fn is_comparison(op: Operator) -> bool {
+    matches!(
+        op,
+        Operator::Eq | Operator::Lt | Operator::Gt | Operator::Le | Operator::Ge
+    )
+}
+
 #[derive(Debug)]
 pub struct SourceRange {
     pub start_line: u32,
@@ -7,6 +20,89 @@ pub struct SourceRange {
     pub length: usize,
 }
 
+impl SourceRange {
+    /// The range covered by an identifier's source span.
+    fn of(identifier: &Identifier) -> Self {
+        Self {
+            start_line: identifier.0.location_line(),
+            start_column: identifier.0.get_utf8_column(),
+            length: identifier.0.fragment().len(),
+        }
+    }
+
+    /// A placeholder range for a diagnostic that has no identifier to point at,
+    /// such as a mismatch between two literal operands.
+    fn unknown() -> Self {
+        Self {
+            start_line: 0,
+            start_column: 0,
+            length: 0,
+        }
+    }
+}
+
+/// A problem found by the semantic-analysis pass. Each one carries the source
+/// range it applies to, so it can flow through the same diagnostics path as
+/// parse errors.
+#[derive(Debug)]
+pub struct TypeError {
+    pub range: SourceRange,
+    pub kind: TypeErrorKind,
+}
+
+#[derive(Debug)]
+pub enum TypeErrorKind {
+    /// A `Name` didn't resolve to a parameter or `let` binding in scope.
+    UnresolvedName(String),
+    /// A call named a function that isn't declared.
+    UnknownFunction(String),
+    /// A call passed the wrong number of arguments.
+    WrongArgCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The two sides of an arithmetic expression have incompatible types.
+    MismatchedOperands { lhs: String, rhs: String },
+    /// A function body's inferred type doesn't match its declared return type.
+    ReturnTypeMismatch { declared: String, inferred: String },
+}
+
+impl fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeErrorKind::UnresolvedName(name) => {
+                write!(f, "cannot find '{name}' in this scope")
+            }
+            TypeErrorKind::UnknownFunction(name) => {
+                write!(f, "call to undeclared function '{name}'")
+            }
+            TypeErrorKind::WrongArgCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function '{function}' expects {expected} argument(s) but {found} were given"
+            ),
+            TypeErrorKind::MismatchedOperands { lhs, rhs } => {
+                write!(f, "cannot combine operands of type '{lhs}' and '{rhs}'")
+            }
+            TypeErrorKind::ReturnTypeMismatch { declared, inferred } => write!(
+                f,
+                "function returns '{inferred}' but its declared return type is '{declared}'"
+            ),
+        }
+    }
+}
+
+/// A function's signature: the declared type of each parameter and the return
+/// type.
+struct Signature {
+    param_types: Vec<String>,
+    return_type: String,
+}
+
 impl<'i> AbstractSyntaxTree<'i> {
     /// Iterates over all functions in the file. Returns each function's name,
     /// and the source code range where that name is found.
@@ -25,4 +121,256 @@ impl<'i> AbstractSyntaxTree<'i> {
             )
         })
     }
+
+    /// Type-check the whole program, returning every problem found. An empty
+    /// vec means the program is well-typed as far as this pass can tell.
+    pub fn typecheck(&self) -> Vec<TypeError> {
+        let signatures: HashMap<String, Signature> = self
+            .functions
+            .iter()
+            .map(|func| {
+                (
+                    func.fn_name.to_string(),
+                    Signature {
+                        param_types: func.params.iter().map(|p| p.kcl_type.to_string()).collect(),
+                        return_type: func.return_type.to_string(),
+                    },
+                )
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+        for func in &self.functions {
+            let mut scope: HashMap<String, String> = func
+                .params
+                .iter()
+                .map(|p| (p.name.to_string(), p.kcl_type.to_string()))
+                .collect();
+            let inferred = check_expr(&func.body, &mut scope, &signatures, &mut errors);
+            // Only flag a mismatch when we could actually infer a concrete type.
+            if let Some(inferred) = inferred {
+                let declared = func.return_type.to_string();
+                if inferred != declared {
+                    errors.push(TypeError {
+                        range: SourceRange::of(&func.return_type),
+                        kind: TypeErrorKind::ReturnTypeMismatch { declared, inferred },
+                    });
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Check one expression against the current scope, pushing any errors found and
+/// returning its inferred type when one can be determined. Literal numbers have
+/// no nominal type, so they (and anything built only from them) infer to
+/// `None`, which suppresses spurious mismatch errors.
+fn check_expr(
+    expr: &Expression,
+    scope: &mut HashMap<String, String>,
+    signatures: &HashMap<String, Signature>,
+    errors: &mut Vec<TypeError>,
+) -> Option<String> {
+    match expr {
+        Expression::Number(_) | Expression::Float(_) => None,
+        Expression::Str(_) => Some("String".to_owned()),
+        Expression::Bool(_) => Some("Bool".to_owned()),
+        Expression::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            check_expr(cond, scope, signatures, errors);
+            let then_ty = check_expr(then_branch, scope, signatures, errors);
+            let else_ty = check_expr(else_branch, scope, signatures, errors);
+            // Both branches share the conditional's type; prefer the one we
+            // could infer.
+            then_ty.or(else_ty)
+        }
+        Expression::Name(ident) => {
+            let name = ident.to_string();
+            match scope.get(&name) {
+                // An empty stored type means "bound but of unknown type".
+                Some(ty) => (!ty.is_empty()).then(|| ty.clone()),
+                None => {
+                    errors.push(TypeError {
+                        range: SourceRange::of(ident),
+                        kind: TypeErrorKind::UnresolvedName(name),
+                    });
+                    None
+                }
+            }
+        }
+        Expression::Arithmetic { lhs, op, rhs } => {
+            let lhs_ty = check_expr(lhs, scope, signatures, errors);
+            let rhs_ty = check_expr(rhs, scope, signatures, errors);
+            if let (Some(l), Some(r)) = (&lhs_ty, &rhs_ty) {
+                if l != r {
+                    // Anchor the error at whichever operand carries a source
+                    // span, preferring the right-hand side. Calls carry one
+                    // just like names do, so a mismatch between two calls
+                    // (`f() + g()`) is still reported rather than dropped. Only
+                    // a mismatch between two span-less literals (e.g. a string
+                    // and a bool) falls back to an unknown location.
+                    let range = operand_range(rhs)
+                        .or_else(|| operand_range(lhs))
+                        .unwrap_or_else(SourceRange::unknown);
+                    errors.push(TypeError {
+                        range,
+                        kind: TypeErrorKind::MismatchedOperands {
+                            lhs: l.clone(),
+                            rhs: r.clone(),
+                        },
+                    });
+                    return None;
+                }
+            }
+            // Comparisons yield a boolean; arithmetic yields the operand type.
+            if is_comparison(*op) {
+                Some("Bool".to_owned())
+            } else {
+                lhs_ty.or(rhs_ty)
+            }
+        }
+        Expression::LetIn { r#let, r#in } => {
+            let mut inner = scope.clone();
+            for assignment in r#let {
+                let ty = check_expr(&assignment.value, &mut inner, signatures, errors);
+                // `let` bindings shadow outer names within the block.
+                inner.insert(
+                    assignment.identifier.to_string(),
+                    ty.unwrap_or_default(),
+                );
+            }
+            check_expr(r#in, &mut inner, signatures, errors)
+        }
+        Expression::FnInvocation(FnInvocation { fn_name, args }) => {
+            for arg in args {
+                check_expr(arg, scope, signatures, errors);
+            }
+            let name = fn_name.to_string();
+            match signatures.get(&name) {
+                None => {
+                    errors.push(TypeError {
+                        range: SourceRange::of(fn_name),
+                        kind: TypeErrorKind::UnknownFunction(name),
+                    });
+                    None
+                }
+                Some(sig) => {
+                    if sig.param_types.len() != args.len() {
+                        errors.push(TypeError {
+                            range: SourceRange::of(fn_name),
+                            kind: TypeErrorKind::WrongArgCount {
+                                function: name,
+                                expected: sig.param_types.len(),
+                                found: args.len(),
+                            },
+                        });
+                    }
+                    Some(sig.return_type.clone())
+                }
+            }
+        }
+    }
+}
+
+/// The source range of the first span-carrying sub-expression, used to anchor
+/// operand-type errors. Names and calls both carry an identifier span; literals
+/// carry none, so an expression built only from literals returns `None`.
+fn operand_range(expr: &Expression) -> Option<SourceRange> {
+    match expr {
+        Expression::Name(ident) => Some(SourceRange::of(ident)),
+        Expression::FnInvocation(FnInvocation { fn_name, .. }) => Some(SourceRange::of(fn_name)),
+        Expression::Arithmetic { lhs, rhs, .. } => {
+            operand_range(lhs).or_else(|| operand_range(rhs))
+        }
+        Expression::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => operand_range(cond)
+            .or_else(|| operand_range(then_branch))
+            .or_else(|| operand_range(else_branch)),
+        Expression::LetIn { r#let, r#in } => r#let
+            .iter()
+            .find_map(|assignment| operand_range(&assignment.value))
+            .or_else(|| operand_range(r#in)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `src`, asserting it is free of parse errors, and type-check it.
+    fn kinds(src: &str) -> Vec<TypeErrorKind> {
+        let (ast, diagnostics) = crate::parse(src);
+        assert!(diagnostics.is_empty(), "test program did not parse: {src}");
+        ast.typecheck().into_iter().map(|err| err.kind).collect()
+    }
+
+    #[test]
+    fn unresolved_name() {
+        assert!(matches!(
+            kinds("f = ( -> Int) => x").as_slice(),
+            [TypeErrorKind::UnresolvedName(name)] if name == "x"
+        ));
+    }
+
+    #[test]
+    fn unknown_function() {
+        assert!(matches!(
+            kinds("f = ( -> Int) => g()").as_slice(),
+            [TypeErrorKind::UnknownFunction(name)] if name == "g"
+        ));
+    }
+
+    #[test]
+    fn wrong_arg_count() {
+        assert!(matches!(
+            kinds("g = (a: Int -> Int) => a\nf = ( -> Int) => g()").as_slice(),
+            [TypeErrorKind::WrongArgCount { function, expected: 1, found: 0 }]
+                if function == "g"
+        ));
+    }
+
+    #[test]
+    fn mismatched_operands() {
+        assert!(matches!(
+            kinds("f = (a: Int, b: Str -> Int) => (a + b)").as_slice(),
+            [TypeErrorKind::MismatchedOperands { lhs, rhs }]
+                if lhs == "Int" && rhs == "Str"
+        ));
+    }
+
+    #[test]
+    fn mismatched_operands_between_two_calls() {
+        // Neither operand is a bare name, but both calls carry a span, so the
+        // mismatch must still be reported rather than silently dropped.
+        let src = "f = ( -> Int) => 1\n\
+                   g = ( -> String) => \"x\"\n\
+                   h = ( -> Int) => (f() + g())";
+        assert!(matches!(
+            kinds(src).as_slice(),
+            [TypeErrorKind::MismatchedOperands { lhs, rhs }]
+                if lhs == "Int" && rhs == "String"
+        ));
+    }
+
+    #[test]
+    fn return_type_mismatch() {
+        assert!(matches!(
+            kinds("f = (s: Str -> Int) => s").as_slice(),
+            [TypeErrorKind::ReturnTypeMismatch { declared, inferred }]
+                if declared == "Int" && inferred == "Str"
+        ));
+    }
+
+    #[test]
+    fn well_typed_program_has_no_errors() {
+        assert!(kinds("f = (a: Int -> Int) => (a + a)").is_empty());
+    }
 }